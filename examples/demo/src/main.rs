@@ -1,6 +1,5 @@
 #![feature(async_closure)]
 
-use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -21,7 +20,9 @@ use rq_tower::rq::{
     QRCodeImageFetch,
 };
 use rq_tower::rq::{LoginResponse, QRCodeState};
+use rq_tower::login::qr::{QrSink, TerminalQr};
 use rq_tower::service::builder::RQServiceBuilder;
+use rq_tower::session::{FileSessionStore, Session, SessionStore};
 
 use crate::handlers::print::{print_friend, print_group};
 
@@ -57,9 +58,17 @@ async fn main() {
         })
         .build();
 
+    // 加密会话存储（device + 重连 token），口令来自环境变量
+    let store = FileSessionStore::new(
+        "session.bin",
+        std::env::var("SESSION_PASSPHRASE").unwrap_or_else(|_| "rq-tower".into()),
+    );
+    let session = load_or_init_session(&store).await;
+    let device = session.device.clone();
+
     // 创建 client
     let client = Arc::new(Client::new(
-        load_device_or_random().await,
+        device.clone(),
         get_version(Protocol::IPad),
         service,
     ));
@@ -80,10 +89,22 @@ async fn main() {
     let uin = std::env::var("UIN").map(|u| u.parse::<i64>().expect("uin is not i64"));
 
     let password = std::env::var("PASSWORD");
-    if uin.is_ok() && password.is_ok() {
-        password_login(&client, uin.clone().unwrap(), password.clone().unwrap()).await;
-    } else {
-        qrcode_login(&client).await;
+    // 优先用已保存的重连 token 登录，令牌登录失败时才回退到交互登录，
+    // 这样落盘的 token 的读取路径才真正被走到。
+    let mut logged_in = false;
+    if let Some(token) = session.token.clone() {
+        tracing::info!("尝试使用已保存的会话令牌登录");
+        match client.token_login(token.0).await {
+            Ok(LoginResponse::Success(_)) => logged_in = true,
+            other => tracing::warn!("令牌登录失败，改为交互登录: {:?}", other),
+        }
+    }
+    if !logged_in {
+        if uin.is_ok() && password.is_ok() {
+            password_login(&client, uin.clone().unwrap(), password.clone().unwrap()).await;
+        } else {
+            qrcode_login(&client, &TerminalQr).await;
+        }
     }
 
     after_login(&client).await;
@@ -99,14 +120,20 @@ async fn main() {
             .expect("failed to reload group list");
         tracing::info!("加载群 {} 个", client.groups.read().await.len());
     }
-    // 登录成功后生成 token，用于掉线重连
-    let token = client.gen_token().await;
+    // 登录成功后生成 token，用于掉线重连，并加密落盘
+    let token = Token(client.gen_token().await);
+    store
+        .save(&Session {
+            device,
+            token: Some(token.clone()),
+        })
+        .expect("failed to persist session");
     // 阻塞到掉线
     handle.await.ok();
     // 自动重连
     auto_reconnect(
         client,
-        Credential::Token(Token(token)),
+        Credential::Token(token),
         Duration::from_secs(10),
         10,
         DefaultConnector,
@@ -115,7 +142,7 @@ async fn main() {
 }
 
 // 扫码登录
-async fn qrcode_login(client: &Arc<Client>) {
+async fn qrcode_login(client: &Arc<Client>, qr_sink: &dyn QrSink) {
     let mut resp = client.fetch_qrcode().await.expect("failed to fetch qrcode");
     let mut image_sig = Bytes::new();
     loop {
@@ -124,11 +151,10 @@ async fn qrcode_login(client: &Arc<Client>) {
                 ref image_data,
                 ref sig,
             }) => {
-                tokio::fs::write("qrcode.png", &image_data)
-                    .await
-                    .expect("failed to write file");
+                qr_sink
+                    .present(image_data, sig)
+                    .expect("failed to present qrcode");
                 image_sig = sig.clone();
-                tracing::info!("二维码: qrcode.png");
             }
             QRCodeState::WaitingForScan => {
                 tracing::info!("二维码待扫描")
@@ -143,11 +169,10 @@ async fn qrcode_login(client: &Arc<Client>) {
                     ref sig,
                 }) = client.fetch_qrcode().await.expect("failed to fetch qrcode")
                 {
-                    tokio::fs::write("qrcode.png", &image_data)
-                        .await
-                        .expect("failed to write file");
+                    qr_sink
+                        .present(image_data, sig)
+                        .expect("failed to present qrcode");
                     image_sig = sig.clone();
-                    tracing::info!("二维码: qrcode.png");
                 }
             }
             QRCodeState::Confirmed(QRCodeConfirmed {
@@ -256,20 +281,16 @@ async fn password_login(client: &Arc<Client>, uin: i64, password: String) {
     }
 }
 
-async fn load_device_or_random() -> Device {
-    match Path::new("device.json").exists() {
-        true => serde_json::from_str(
-            &tokio::fs::read_to_string("device.json")
-                .await
-                .expect("failed to read device.json"),
-        )
-        .expect("failed to parse device info"),
-        false => {
-            let d = Device::random();
-            tokio::fs::write("device.json", serde_json::to_string(&d).unwrap())
-                .await
-                .expect("failed to write device info to file");
-            d
+async fn load_or_init_session(store: &FileSessionStore) -> Session {
+    match store.load().expect("failed to read session store") {
+        Some(session) => session,
+        None => {
+            let session = Session {
+                device: Device::random(),
+                token: None,
+            };
+            store.save(&session).expect("failed to persist session");
+            session
         }
     }
 }
@@ -0,0 +1,266 @@
+//! Declarative command router for message handlers.
+//!
+//! [`CommandRouter`] layers on top of [`RQServiceBuilder`] so bots register
+//! commands by prefix (e.g. `!weather <city>`) instead of hand-rolling prefix
+//! checks and argument splitting in every handler. Incoming group messages are
+//! matched against the registered commands, positional and `--named` arguments
+//! are extracted, and the matching handler is dispatched with the parsed
+//! [`Args`]; unmatched messages fall through to an optional default handler.
+//!
+//! Each command can carry guards — a group allow-list and an arbitrary sender
+//! predicate — and the registry can enumerate itself to power a built-in
+//! `!help`.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::rq::client::event::GroupMessageEvent;
+use crate::service::builder::RQServiceBuilder;
+
+type BoxFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+type CommandFn = Arc<dyn Fn(CommandContext) -> BoxFuture + Send + Sync>;
+type GuardFn = Arc<dyn Fn(&GroupMessageEvent) -> bool + Send + Sync>;
+
+/// Parsed arguments handed to a command handler.
+#[derive(Debug, Default, Clone)]
+pub struct Args {
+    /// Positional tokens following the command keyword.
+    pub positional: Vec<String>,
+    /// `--key value` / `--flag` options.
+    pub named: HashMap<String, String>,
+}
+
+impl Args {
+    /// The `index`-th positional argument, if present.
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.positional.get(index).map(String::as_str)
+    }
+
+    /// The value of a `--named` argument, if present.
+    pub fn named(&self, key: &str) -> Option<&str> {
+        self.named.get(key).map(String::as_str)
+    }
+}
+
+/// The event plus its parsed arguments, passed to a matched command.
+pub struct CommandContext {
+    pub event: GroupMessageEvent,
+    pub args: Args,
+}
+
+struct Command {
+    keyword: String,
+    usage: String,
+    description: String,
+    groups: Option<Vec<i64>>,
+    guard: Option<GuardFn>,
+    handler: CommandFn,
+}
+
+impl Command {
+    /// Whether this command may run for `event` given its guards.
+    fn permitted(&self, event: &GroupMessageEvent) -> bool {
+        if let Some(groups) = &self.groups {
+            if !groups.contains(&event.message.group_code) {
+                return false;
+            }
+        }
+        match &self.guard {
+            Some(g) => g(event),
+            None => true,
+        }
+    }
+}
+
+/// Builder for a single command registration.
+pub struct CommandBuilder {
+    router: CommandRouter,
+    command: Command,
+}
+
+impl CommandBuilder {
+    /// Restrict this command to the given group codes.
+    pub fn allow_groups(mut self, groups: impl IntoIterator<Item = i64>) -> Self {
+        self.command.groups = Some(groups.into_iter().collect());
+        self
+    }
+
+    /// Guard the command behind an arbitrary predicate over the event, e.g. a
+    /// sender permission check.
+    pub fn guard<G>(mut self, guard: G) -> Self
+    where
+        G: Fn(&GroupMessageEvent) -> bool + Send + Sync + 'static,
+    {
+        self.command.guard = Some(Arc::new(guard));
+        self
+    }
+
+    /// One-line description shown by `!help`.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.command.description = description.into();
+        self
+    }
+
+    /// Finish this command and return to the router.
+    pub fn register(mut self) -> CommandRouter {
+        self.router.commands.push(self.command);
+        self.router
+    }
+}
+
+/// Routes group messages to registered commands.
+pub struct CommandRouter {
+    commands: Vec<Command>,
+    default: Option<CommandFn>,
+    help: bool,
+}
+
+impl Default for CommandRouter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CommandRouter {
+    pub fn new() -> Self {
+        Self {
+            commands: Vec::new(),
+            default: None,
+            help: false,
+        }
+    }
+
+    /// Register a command matched by its `pattern`'s leading keyword.
+    ///
+    /// The pattern is also the `!help` usage string, e.g. `"!weather <city>"`.
+    pub fn command<F, Fut>(self, pattern: impl Into<String>, handler: F) -> CommandBuilder
+    where
+        F: Fn(CommandContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let usage = pattern.into();
+        let keyword = usage
+            .split_whitespace()
+            .next()
+            .unwrap_or(&usage)
+            .to_string();
+        CommandBuilder {
+            command: Command {
+                keyword,
+                usage,
+                description: String::new(),
+                groups: None,
+                guard: None,
+                handler: Arc::new(move |ctx| Box::pin(handler(ctx))),
+            },
+            router: self,
+        }
+    }
+
+    /// Handler invoked when no command matches.
+    pub fn default_handler<F, Fut>(mut self, handler: F) -> Self
+    where
+        F: Fn(CommandContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.default = Some(Arc::new(move |ctx| Box::pin(handler(ctx))));
+        self
+    }
+
+    /// Enable a built-in `!help` command listing every registered command.
+    pub fn with_help(mut self) -> Self {
+        self.help = true;
+        self
+    }
+
+    /// Attach the router to `builder` as a group-message handler.
+    pub fn attach(self, builder: RQServiceBuilder) -> RQServiceBuilder {
+        let router = Arc::new(self);
+        builder.on_group_message(move |event: GroupMessageEvent| {
+            let router = router.clone();
+            async move { router.dispatch(event).await }
+        })
+    }
+
+    async fn dispatch(&self, event: GroupMessageEvent) {
+        let text = event.message.elements.to_string();
+        let keyword = match text.split_whitespace().next() {
+            Some(k) => k,
+            None => return,
+        };
+
+        if self.help && keyword == "!help" {
+            self.send_help(&event).await;
+            return;
+        }
+
+        for command in &self.commands {
+            if command.keyword == keyword {
+                if !command.permitted(&event) {
+                    return;
+                }
+                let args = parse_args(&text);
+                (command.handler)(CommandContext { event, args }).await;
+                return;
+            }
+        }
+
+        if let Some(default) = &self.default {
+            let args = parse_args(&text);
+            default(CommandContext { event, args }).await;
+        }
+    }
+
+    async fn send_help(&self, event: &GroupMessageEvent) {
+        use crate::rq::msg::elem::Text;
+        use crate::rq::msg::MessageChain;
+
+        let mut chain = MessageChain::default();
+        chain.push(Text::new(self.help_text()));
+        event
+            .client
+            .send_group_message(event.message.group_code, chain)
+            .await
+            .ok();
+    }
+
+    /// Render the help listing for all registered commands.
+    pub fn help_text(&self) -> String {
+        let mut out = String::from("可用命令:\n");
+        for command in &self.commands {
+            out.push_str(&command.usage);
+            if !command.description.is_empty() {
+                out.push_str("  - ");
+                out.push_str(&command.description);
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Split `text` into positional tokens and `--named` options, dropping the
+/// leading command keyword.
+fn parse_args(text: &str) -> Args {
+    let mut args = Args::default();
+    let mut tokens = text.split_whitespace();
+    tokens.next(); // command keyword
+    while let Some(token) = tokens.next() {
+        if let Some(key) = token.strip_prefix("--") {
+            match tokens.clone().next() {
+                Some(value) if !value.starts_with("--") => {
+                    tokens.next();
+                    args.named.insert(key.to_string(), value.to_string());
+                }
+                _ => {
+                    args.named.insert(key.to_string(), String::new());
+                }
+            }
+        } else {
+            args.positional.push(token.to_string());
+        }
+    }
+    args
+}
@@ -0,0 +1,9 @@
+pub mod command;
+pub mod login;
+pub mod metrics;
+pub mod service;
+pub mod session;
+
+pub mod rq {
+    pub use rs_qq::*;
+}
@@ -0,0 +1,315 @@
+// axum = "0.6"
+// base64 = "0.21"
+// hex = "0.4"
+
+//! HTTP front-end for the login flows.
+//!
+//! [`router`] returns an [`axum::Router`] that owns a single [`Client`] and
+//! drives its QR-code / password login state machines through a handful of
+//! JSON endpoints:
+//!
+//! | method | path                     | purpose                               |
+//! |--------|--------------------------|---------------------------------------|
+//! | POST   | `/login/qr/start`        | fetch a QR image (base64 PNG + token) |
+//! | GET    | `/login/qr/poll`         | poll the QR scan/confirm state        |
+//! | POST   | `/login/password/start`  | start a uin + password login          |
+//! | POST   | `/login/captcha`         | submit a slider/captcha ticket        |
+//! | POST   | `/login/sms`             | submit an SMS verification code        |
+//! | POST   | `/login/device-lock`     | run the device-lock login step        |
+//!
+//! Each in-progress QR login is keyed by the opaque token returned from
+//! `/login/qr/start`, so several accounts can log in through the same router
+//! concurrently without clobbering one another. On a successful login a
+//! reconnect token is generated, sealed through the shared [`SessionStore`]
+//! (so credentials never touch disk unencrypted) and returned to the caller,
+//! mirroring the `gen_token` step of the interactive flows.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use base64::Engine;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::rq::device::Device;
+use crate::rq::ext::reconnect::Token;
+use crate::rq::{
+    Client, LoginDeviceLocked, LoginNeedCaptcha, LoginResponse, LoginUnknownStatus, QRCodeConfirmed,
+    QRCodeImageFetch, QRCodeState,
+};
+use crate::session::{Session, SessionStore};
+
+/// Shared session store behind the login endpoints.
+type Store = Arc<dyn SessionStore + Send + Sync>;
+
+/// Shared state behind the login endpoints.
+#[derive(Clone)]
+pub struct LoginState {
+    client: Arc<Client>,
+    /// In-progress QR logins keyed by the token handed back from `qr_start`;
+    /// the value is the image `sig` that `qr_poll` queries against.
+    qr_sessions: Arc<Mutex<HashMap<String, Bytes>>>,
+    /// Device fingerprint persisted alongside the reconnect token.
+    device: Device,
+    /// Where the reconnect session is sealed on success.
+    store: Store,
+}
+
+impl LoginState {
+    /// Build a login front-end over `client`, sealing the reconnect session
+    /// through `store`.
+    pub fn new(client: Arc<Client>, device: Device, store: Store) -> Self {
+        Self {
+            client,
+            qr_sessions: Arc::new(Mutex::new(HashMap::new())),
+            device,
+            store,
+        }
+    }
+}
+
+/// Build the login [`Router`] for `client`.
+pub fn router(client: Arc<Client>, device: Device, store: Store) -> Router {
+    router_with_state(LoginState::new(client, device, store))
+}
+
+/// Build the login [`Router`] from a pre-configured [`LoginState`].
+pub fn router_with_state(state: LoginState) -> Router {
+    Router::new()
+        .route("/login/qr/start", post(qr_start))
+        .route("/login/qr/poll", get(qr_poll))
+        .route("/login/password/start", post(password_start))
+        .route("/login/captcha", post(submit_captcha))
+        .route("/login/sms", post(submit_sms))
+        .route("/login/device-lock", post(device_lock))
+        .with_state(state)
+}
+
+/// The error JSON returned for any failed protocol call.
+type ApiError = (StatusCode, Json<ErrorBody>);
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+fn bad_gateway(e: impl std::fmt::Display) -> ApiError {
+    (
+        StatusCode::BAD_GATEWAY,
+        Json(ErrorBody {
+            error: e.to_string(),
+        }),
+    )
+}
+
+#[derive(Serialize)]
+struct QrStartResp {
+    /// base64-encoded PNG of the QR image.
+    image: String,
+    /// Opaque polling token — currently the hex-encoded image `sig`.
+    token: String,
+}
+
+async fn qr_start(State(st): State<LoginState>) -> Result<Json<QrStartResp>, ApiError> {
+    let resp = st.client.fetch_qrcode().await.map_err(bad_gateway)?;
+    match resp {
+        QRCodeState::ImageFetch(QRCodeImageFetch { image_data, sig }) => {
+            let token = hex::encode(&sig);
+            st.qr_sessions.lock().await.insert(token.clone(), sig);
+            Ok(Json(QrStartResp {
+                image: base64::engine::general_purpose::STANDARD.encode(&image_data),
+                token,
+            }))
+        }
+        other => Err(bad_gateway(format!("unexpected qrcode state: {:?}", other))),
+    }
+}
+
+#[derive(Deserialize)]
+struct QrPollReq {
+    /// The polling token returned from `qr_start`.
+    token: String,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+enum QrPollResp {
+    WaitingForScan,
+    WaitingForConfirm,
+    Timeout,
+    Canceled,
+    Confirmed { token: String },
+}
+
+async fn qr_poll(
+    State(st): State<LoginState>,
+    Query(req): Query<QrPollReq>,
+) -> Result<Json<QrPollResp>, ApiError> {
+    let sig = st
+        .qr_sessions
+        .lock()
+        .await
+        .get(&req.token)
+        .cloned()
+        .ok_or_else(|| bad_gateway("no qr login in progress"))?;
+    let resp = st
+        .client
+        .query_qrcode_result(&sig)
+        .await
+        .map_err(bad_gateway)?;
+    match resp {
+        QRCodeState::ImageFetch(_) | QRCodeState::WaitingForScan => {
+            Ok(Json(QrPollResp::WaitingForScan))
+        }
+        QRCodeState::WaitingForConfirm => Ok(Json(QrPollResp::WaitingForConfirm)),
+        QRCodeState::Timeout => Ok(Json(QrPollResp::Timeout)),
+        QRCodeState::Canceled => Ok(Json(QrPollResp::Canceled)),
+        QRCodeState::Confirmed(QRCodeConfirmed {
+            tmp_pwd,
+            tmp_no_pic_sig,
+            tgt_qr,
+            ..
+        }) => {
+            let mut login_resp = st
+                .client
+                .qrcode_login(&tmp_pwd, &tmp_no_pic_sig, &tgt_qr)
+                .await
+                .map_err(bad_gateway)?;
+            if let LoginResponse::DeviceLockLogin { .. } = login_resp {
+                login_resp = st.client.device_lock_login().await.map_err(bad_gateway)?;
+            }
+            match login_resp {
+                LoginResponse::Success(_) => {
+                    st.qr_sessions.lock().await.remove(&req.token);
+                    let token = finish_login(&st).await?;
+                    Ok(Json(QrPollResp::Confirmed { token }))
+                }
+                other => Err(bad_gateway(format!("qrcode login failed: {:?}", other))),
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PasswordReq {
+    uin: i64,
+    password: String,
+}
+
+async fn password_start(
+    State(st): State<LoginState>,
+    Json(req): Json<PasswordReq>,
+) -> Result<Json<LoginStep>, ApiError> {
+    let resp = st
+        .client
+        .password_login(req.uin, &req.password)
+        .await
+        .map_err(bad_gateway)?;
+    map_login_response(&st, resp).await
+}
+
+#[derive(Deserialize)]
+struct TicketReq {
+    ticket: String,
+}
+
+async fn submit_captcha(
+    State(st): State<LoginState>,
+    Json(req): Json<TicketReq>,
+) -> Result<Json<LoginStep>, ApiError> {
+    let resp = st
+        .client
+        .submit_ticket(&req.ticket)
+        .await
+        .map_err(bad_gateway)?;
+    map_login_response(&st, resp).await
+}
+
+#[derive(Deserialize)]
+struct SmsReq {
+    code: String,
+}
+
+async fn submit_sms(
+    State(st): State<LoginState>,
+    Json(req): Json<SmsReq>,
+) -> Result<Json<LoginStep>, ApiError> {
+    let resp = st
+        .client
+        .submit_sms_code(&req.code)
+        .await
+        .map_err(bad_gateway)?;
+    map_login_response(&st, resp).await
+}
+
+async fn device_lock(State(st): State<LoginState>) -> Result<Json<LoginStep>, ApiError> {
+    let resp = st.client.device_lock_login().await.map_err(bad_gateway)?;
+    map_login_response(&st, resp).await
+}
+
+/// A single step of a password login, as seen by the caller.
+#[derive(Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+enum LoginStep {
+    Success { token: String },
+    DeviceLocked { sms_phone: String, verify_url: String, message: String },
+    NeedCaptcha { verify_url: String },
+    DeviceLockLogin,
+    AccountFrozen,
+    TooManySmsRequest,
+    Unknown { status: i32, message: String },
+}
+
+async fn map_login_response(
+    st: &LoginState,
+    resp: LoginResponse,
+) -> Result<Json<LoginStep>, ApiError> {
+    let step = match resp {
+        LoginResponse::Success(_) => {
+            let token = finish_login(st).await?;
+            LoginStep::Success { token }
+        }
+        LoginResponse::DeviceLocked(LoginDeviceLocked {
+            sms_phone,
+            verify_url,
+            message,
+            ..
+        }) => LoginStep::DeviceLocked {
+            sms_phone: sms_phone.unwrap_or_default(),
+            verify_url: verify_url.unwrap_or_default(),
+            message: message.unwrap_or_default(),
+        },
+        LoginResponse::NeedCaptcha(LoginNeedCaptcha { verify_url, .. }) => LoginStep::NeedCaptcha {
+            verify_url: verify_url.unwrap_or_default(),
+        },
+        LoginResponse::DeviceLockLogin(_) => LoginStep::DeviceLockLogin,
+        LoginResponse::AccountFrozen => LoginStep::AccountFrozen,
+        LoginResponse::TooManySMSRequest => LoginStep::TooManySmsRequest,
+        LoginResponse::UnknownStatus(LoginUnknownStatus {
+            status, message, ..
+        }) => LoginStep::Unknown { status, message },
+    };
+    Ok(Json(step))
+}
+
+/// Seal the reconnect session after a successful login and return the token.
+///
+/// Persistence goes through the shared [`SessionStore`] so the token is
+/// encrypted at rest, matching the interactive flow rather than writing
+/// `token.json` in plaintext.
+async fn finish_login(st: &LoginState) -> Result<String, ApiError> {
+    let token = Token(st.client.gen_token().await);
+    let json = serde_json::to_string(&token).map_err(bad_gateway)?;
+    st.store
+        .save(&Session {
+            device: st.device.clone(),
+            token: Some(token),
+        })
+        .map_err(bad_gateway)?;
+    Ok(json)
+}
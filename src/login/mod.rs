@@ -0,0 +1,9 @@
+//! Non-interactive drivers for the rs-qq login state machines.
+//!
+//! The [`http`] subsystem exposes the QR-code and password login flows as an
+//! HTTP API so rq-tower can be driven from a web UI or a multi-account manager
+//! instead of requiring a terminal (and a local filesystem for `qrcode.png`)
+//! per bot.
+
+pub mod http;
+pub mod qr;
@@ -0,0 +1,172 @@
+// image = "0.24"
+
+//! Pluggable output for the login QR code.
+//!
+//! The demo's `qrcode_login` hands the fetched QR image to a [`QrSink`] instead
+//! of hard-coding a write to `qrcode.png`. The default [`TerminalQr`] renders
+//! the image as Unicode half-block art straight to the terminal, so the flow
+//! works over SSH or in container logs; [`PngFile`] keeps the old
+//! write-to-disk behaviour and [`BytesSink`] hands the raw bytes back to a
+//! caller (e.g. the HTTP login API).
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Light-module quiet zone drawn around the code so scanners can lock on.
+/// The QR spec mandates four modules of margin.
+const QUIET_ZONE: usize = 4;
+
+/// A place to deliver the login QR image to the user.
+///
+/// `image_data` is the PNG returned by the server and `sig` is the image's
+/// login signature, for sinks that prefer to key on it.
+pub trait QrSink: Send + Sync {
+    fn present(&self, image_data: &[u8], sig: &[u8]) -> io::Result<()>;
+}
+
+/// Renders the QR image as Unicode half-block art on standard output.
+#[derive(Default)]
+pub struct TerminalQr;
+
+impl QrSink for TerminalQr {
+    fn present(&self, image_data: &[u8], _sig: &[u8]) -> io::Result<()> {
+        let art = render_half_blocks(image_data)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let stdout = io::stdout();
+        let mut lock = stdout.lock();
+        writeln!(lock, "请使用手机 QQ 扫描二维码登录:")?;
+        lock.write_all(art.as_bytes())?;
+        lock.flush()
+    }
+}
+
+/// Writes the raw PNG bytes to a file (the original `qrcode.png` behaviour).
+pub struct PngFile {
+    path: PathBuf,
+}
+
+impl PngFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Default for PngFile {
+    fn default() -> Self {
+        Self::new("qrcode.png")
+    }
+}
+
+impl QrSink for PngFile {
+    fn present(&self, image_data: &[u8], _sig: &[u8]) -> io::Result<()> {
+        std::fs::write(&self.path, image_data)?;
+        tracing::info!("二维码: {}", self.path.display());
+        Ok(())
+    }
+}
+
+/// Captures the most recent QR image bytes for a caller to read back.
+#[derive(Clone, Default)]
+pub struct BytesSink(Arc<Mutex<Option<Vec<u8>>>>);
+
+impl BytesSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The most recently presented image bytes, if any.
+    pub fn get(&self) -> Option<Vec<u8>> {
+        self.0.lock().expect("qr sink poisoned").clone()
+    }
+}
+
+impl QrSink for BytesSink {
+    fn present(&self, image_data: &[u8], _sig: &[u8]) -> io::Result<()> {
+        *self.0.lock().expect("qr sink poisoned") = Some(image_data.to_vec());
+        Ok(())
+    }
+}
+
+/// Recover the QR module grid from the server PNG and render it at true module
+/// resolution — one terminal cell per module — so the printed code stays
+/// scannable. Resampling to an arbitrary pixel width drops or doubles modules
+/// because QQ's PNG has a non-integer pixels-per-module ratio; instead we
+/// detect the module pitch from the top-left finder pattern and sample the
+/// centre of every module, then pack two module rows into each half-block line.
+fn render_half_blocks(image_data: &[u8]) -> Result<String, image::ImageError> {
+    let img = image::load_from_memory(image_data)?.to_luma8();
+    let matrix = decode_modules(&img)
+        .ok_or_else(|| image::ImageError::Parameter(image::error::ParameterError::from_kind(
+            image::error::ParameterErrorKind::Generic("could not locate QR modules".into()),
+        )))?;
+
+    let width = matrix[0].len();
+    let dark = |x: usize, y: usize| matrix.get(y).map(|row| row[x]).unwrap_or(false);
+    let mut out = String::new();
+    let mut y = 0;
+    while y < matrix.len() {
+        for x in 0..width {
+            out.push(match (dark(x, y), dark(x, y + 1)) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            });
+        }
+        out.push('\n');
+        y += 2;
+    }
+    Ok(out)
+}
+
+/// Reconstruct the boolean module grid (`true` = dark) from a luma QR image,
+/// padded with a [`QUIET_ZONE`] light border.
+fn decode_modules(img: &image::GrayImage) -> Option<Vec<Vec<bool>>> {
+    let dark = |x: u32, y: u32| img.get_pixel(x, y)[0] < 128;
+    let (w, h) = img.dimensions();
+
+    // Bounding box of the code itself, ignoring the white quiet zone.
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (w, h, 0u32, 0u32);
+    for y in 0..h {
+        for x in 0..w {
+            if dark(x, y) {
+                min_x = min_x.min(x);
+                min_y = min_y.min(y);
+                max_x = max_x.max(x);
+                max_y = max_y.max(y);
+            }
+        }
+    }
+    if min_x > max_x || min_y > max_y {
+        return None;
+    }
+
+    // The top-left finder pattern is seven modules wide, so the leading dark run
+    // on its first row gives the module pitch in pixels.
+    let mut run = 0u32;
+    for x in min_x..=max_x {
+        if dark(x, min_y) {
+            run += 1;
+        } else {
+            break;
+        }
+    }
+    let module_px = (run as f32 / 7.0).max(1.0);
+    let modules = (((max_x - min_x + 1) as f32) / module_px).round() as usize;
+    if modules == 0 {
+        return None;
+    }
+
+    let mut grid = vec![vec![false; modules + QUIET_ZONE * 2]; modules + QUIET_ZONE * 2];
+    for my in 0..modules {
+        for mx in 0..modules {
+            let px = min_x + ((mx as f32 + 0.5) * module_px) as u32;
+            let py = min_y + ((my as f32 + 0.5) * module_px) as u32;
+            if px < w && py < h && dark(px, py) {
+                grid[my + QUIET_ZONE][mx + QUIET_ZONE] = true;
+            }
+        }
+    }
+    Some(grid)
+}
@@ -0,0 +1,135 @@
+// prometheus = "0.13"
+// tracing-opentelemetry / opentelemetry / opentelemetry-otlp are pulled in
+// behind the `otlp` feature only.
+
+//! Cross-cutting instrumentation for event dispatch.
+//!
+//! Every handler invocation in [`RQServiceBuilder`](crate::service::builder::RQServiceBuilder)
+//! is wrapped in a `tracing` span tagged with the event kind and handler index,
+//! and its latency / outcome is fed into a small Prometheus-style [`Metrics`]
+//! registry that hangs off the built [`RQService`](crate::service::RQService).
+//! Operators can scrape [`Metrics::gather`] to observe dispatch throughput and
+//! per-handler tail latency without touching the individual closures.
+
+use std::sync::Arc;
+
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder};
+
+/// Handle to the dispatch metrics registry.
+///
+/// Cloning is cheap (everything is reference counted) so the same registry is
+/// shared between the builder, the running service and any scrape endpoint.
+#[derive(Clone)]
+pub struct Metrics {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    registry: Registry,
+    events_received: IntCounterVec,
+    events_handled: IntCounterVec,
+    handler_duration: HistogramVec,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    /// Build a fresh registry with the dispatch collectors registered.
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        let events_received = IntCounterVec::new(
+            Opts::new("rq_events_received_total", "events handed to dispatch"),
+            &["kind"],
+        )
+        .expect("valid metric opts");
+        let events_handled = IntCounterVec::new(
+            Opts::new("rq_events_handled_total", "handler invocations by outcome"),
+            &["kind", "outcome"],
+        )
+        .expect("valid metric opts");
+        let handler_duration = HistogramVec::new(
+            HistogramOpts::new("rq_handler_duration_seconds", "handler latency"),
+            &["kind"],
+        )
+        .expect("valid metric opts");
+        registry
+            .register(Box::new(events_received.clone()))
+            .expect("register events_received");
+        registry
+            .register(Box::new(events_handled.clone()))
+            .expect("register events_handled");
+        registry
+            .register(Box::new(handler_duration.clone()))
+            .expect("register handler_duration");
+        Self {
+            inner: Arc::new(Inner {
+                registry,
+                events_received,
+                events_handled,
+                handler_duration,
+            }),
+        }
+    }
+
+    /// Count one event of `kind` arriving at the dispatch table.
+    pub fn event_received(&self, kind: &str) {
+        self.inner.events_received.with_label_values(&[kind]).inc();
+    }
+
+    /// Record the outcome and latency of a single handler invocation.
+    pub fn handler_finished(&self, kind: &str, ok: bool, elapsed: std::time::Duration) {
+        let outcome = if ok { "ok" } else { "error" };
+        self.inner
+            .events_handled
+            .with_label_values(&[kind, outcome])
+            .inc();
+        self.inner
+            .handler_duration
+            .with_label_values(&[kind])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// The underlying registry, for wiring extra collectors or an exporter.
+    pub fn registry(&self) -> &Registry {
+        &self.inner.registry
+    }
+
+    /// Encode the current metrics in the Prometheus text exposition format.
+    pub fn gather(&self) -> String {
+        let mut buf = Vec::new();
+        let encoder = TextEncoder::new();
+        let families = self.inner.registry.gather();
+        encoder.encode(&families, &mut buf).ok();
+        String::from_utf8(buf).unwrap_or_default()
+    }
+}
+
+/// Install a `tracing-opentelemetry` layer that exports dispatch spans over OTLP.
+///
+/// Returns the `OpenTelemetryLayer` so it can be composed into an existing
+/// `tracing_subscriber` registry by the caller. Only available with the
+/// `otlp` feature enabled.
+#[cfg(feature = "otlp")]
+pub fn otlp_layer<S>(
+    endpoint: &str,
+) -> Result<
+    tracing_opentelemetry::OpenTelemetryLayer<S, opentelemetry::sdk::trace::Tracer>,
+    opentelemetry::trace::TraceError,
+>
+where
+    S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+{
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint),
+        )
+        .install_batch(opentelemetry::runtime::Tokio)?;
+    Ok(tracing_opentelemetry::layer().with_tracer(tracer))
+}
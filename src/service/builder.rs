@@ -1,53 +1,88 @@
 use std::convert::Infallible;
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::Duration;
 
 use rs_qq::client::event::{
     DeleteFriendEvent, FriendMessageRecallEvent, FriendPokeEvent, FriendRequestEvent,
-    GroupLeaveEvent, GroupMessageEvent, GroupMessageRecallEvent, GroupMuteEvent,
-    GroupNameUpdateEvent, GroupRequestEvent, MemberPermissionChangeEvent, NewFriendEvent,
-    NewMemberEvent, PrivateMessageEvent, SelfInvitedEvent,
+    GroupDisbandEvent, GroupLeaveEvent, GroupMessageEvent, GroupMessageRecallEvent, GroupMuteEvent,
+    GroupNameUpdateEvent, GroupRequestEvent, GroupTempMessageEvent, KickedOfflineEvent,
+    MSFOfflineEvent, MemberPermissionChangeEvent, NewFriendEvent, NewMemberEvent,
+    PrivateMessageEvent, SelfInvitedEvent,
 };
 use tower::buffer::Buffer;
 use tower::util::BoxCloneService;
-use tower::{Service, ServiceBuilder};
+use tower::{BoxError, Layer, Service, ServiceBuilder, ServiceExt};
+use tracing::Instrument;
 
+use crate::metrics::Metrics;
 use crate::rq::handler::QEvent;
 use crate::service::RQService;
 
+/// Error type flowing through layered handler services. Bare handlers are
+/// infallible; `tower` layers such as `timeout` or `rate_limit` surface their
+/// failures here.
+pub type HandlerError = BoxError;
+
+/// The fully-boxed `QEvent` dispatch service, after any global layers.
+pub type DispatchService = BoxCloneService<QEvent, (), HandlerError>;
+
+type GlobalLayer = Arc<dyn Fn(DispatchService) -> DispatchService + Send + Sync>;
+
 #[derive(Default, Clone)]
 pub struct RQServiceBuilder {
-    login_handlers: Vec<BoxCloneService<i64, (), Infallible>>,
-    group_message_handlers: Vec<BoxCloneService<GroupMessageEvent, (), Infallible>>,
-    private_message_handlers: Vec<BoxCloneService<PrivateMessageEvent, (), Infallible>>,
-    group_request_handlers: Vec<BoxCloneService<GroupRequestEvent, (), Infallible>>,
-    friend_request_handlers: Vec<BoxCloneService<FriendRequestEvent, (), Infallible>>,
-    self_invited_handlers: Vec<BoxCloneService<SelfInvitedEvent, (), Infallible>>,
-    new_member_handlers: Vec<BoxCloneService<NewMemberEvent, (), Infallible>>,
-    group_mute_handlers: Vec<BoxCloneService<GroupMuteEvent, (), Infallible>>,
-    friend_message_recall_handlers: Vec<BoxCloneService<FriendMessageRecallEvent, (), Infallible>>,
-    group_message_recall_handlers: Vec<BoxCloneService<GroupMessageRecallEvent, (), Infallible>>,
-    new_friend_handlers: Vec<BoxCloneService<NewFriendEvent, (), Infallible>>,
-    group_leave_handlers: Vec<BoxCloneService<GroupLeaveEvent, (), Infallible>>,
-    friend_poke_handlers: Vec<BoxCloneService<FriendPokeEvent, (), Infallible>>,
-    group_name_update_handlers: Vec<BoxCloneService<GroupNameUpdateEvent, (), Infallible>>,
-    delete_friend_handlers: Vec<BoxCloneService<DeleteFriendEvent, (), Infallible>>,
+    metrics: Metrics,
+    buffer_bound: Option<usize>,
+    global_layer: Option<GlobalLayer>,
+    login_handlers: Vec<BoxCloneService<i64, (), HandlerError>>,
+    group_message_handlers: Vec<BoxCloneService<GroupMessageEvent, (), HandlerError>>,
+    private_message_handlers: Vec<BoxCloneService<PrivateMessageEvent, (), HandlerError>>,
+    group_request_handlers: Vec<BoxCloneService<GroupRequestEvent, (), HandlerError>>,
+    friend_request_handlers: Vec<BoxCloneService<FriendRequestEvent, (), HandlerError>>,
+    self_invited_handlers: Vec<BoxCloneService<SelfInvitedEvent, (), HandlerError>>,
+    new_member_handlers: Vec<BoxCloneService<NewMemberEvent, (), HandlerError>>,
+    group_mute_handlers: Vec<BoxCloneService<GroupMuteEvent, (), HandlerError>>,
+    friend_message_recall_handlers: Vec<BoxCloneService<FriendMessageRecallEvent, (), HandlerError>>,
+    group_message_recall_handlers: Vec<BoxCloneService<GroupMessageRecallEvent, (), HandlerError>>,
+    new_friend_handlers: Vec<BoxCloneService<NewFriendEvent, (), HandlerError>>,
+    group_leave_handlers: Vec<BoxCloneService<GroupLeaveEvent, (), HandlerError>>,
+    friend_poke_handlers: Vec<BoxCloneService<FriendPokeEvent, (), HandlerError>>,
+    group_name_update_handlers: Vec<BoxCloneService<GroupNameUpdateEvent, (), HandlerError>>,
+    delete_friend_handlers: Vec<BoxCloneService<DeleteFriendEvent, (), HandlerError>>,
     member_permission_change_handlers:
-        Vec<BoxCloneService<MemberPermissionChangeEvent, (), Infallible>>,
+        Vec<BoxCloneService<MemberPermissionChangeEvent, (), HandlerError>>,
+    group_temp_message_handlers: Vec<BoxCloneService<GroupTempMessageEvent, (), HandlerError>>,
+    group_disband_handlers: Vec<BoxCloneService<GroupDisbandEvent, (), HandlerError>>,
+    kicked_offline_handlers: Vec<BoxCloneService<KickedOfflineEvent, (), HandlerError>>,
+    msf_offline_handlers: Vec<BoxCloneService<MSFOfflineEvent, (), HandlerError>>,
 }
 
 macro_rules! call_event {
-    ($($ety: tt: $handler: tt),*) => {
+    ($($ety: tt: $handler: tt: $uin: expr),*) => {
         fn call(&mut self, e: QEvent) -> Self::Future {
             match e {
                 $(
                     QEvent::$ety(e) => {
                         let mut handlers = self.$handler.clone();
+                        let metrics = self.metrics.clone();
+                        let kind = stringify!($ety);
+                        // 会话 uin（群号 / 好友号），在 `e` 被移动进异步块前取出，
+                        // 没有对应会话的事件记为 0。
+                        let uin: i64 = ($uin)(&e).unwrap_or_default();
+                        metrics.event_received(kind);
                         Box::pin(async move {
-                            for h in handlers.iter_mut() {
-                                h.call(e.clone()).await.ok();
+                            for (idx, h) in handlers.iter_mut().enumerate() {
+                                let span = tracing::info_span!(
+                                    "call_event",
+                                    kind,
+                                    uin,
+                                    handler = idx
+                                );
+                                let started = std::time::Instant::now();
+                                let res = h.call(e.clone()).instrument(span).await;
+                                metrics.handler_finished(kind, res.is_ok(), started.elapsed());
                             }
                             Ok(())
                         })
@@ -70,22 +105,26 @@ impl Service<QEvent> for RQServiceBuilder {
     }
 
     call_event!(
-        LoginEvent: login_handlers,
-        GroupMessage: group_message_handlers,
-        PrivateMessage: private_message_handlers,
-        GroupRequest: group_request_handlers,
-        SelfInvited: self_invited_handlers,
-        FriendRequest: friend_request_handlers,
-        NewMember: new_member_handlers,
-        GroupMute: group_mute_handlers,
-        FriendMessageRecall: friend_message_recall_handlers,
-        GroupMessageRecall: group_message_recall_handlers,
-        NewFriend: new_friend_handlers,
-        GroupLeave: group_leave_handlers,
-        FriendPoke: friend_poke_handlers,
-        GroupNameUpdate: group_name_update_handlers,
-        DeleteFriend: delete_friend_handlers,
-        MemberPermissionChange: member_permission_change_handlers
+        LoginEvent: login_handlers: |e: &i64| Some(*e),
+        GroupMessage: group_message_handlers: |e| Some(e.message.group_code),
+        PrivateMessage: private_message_handlers: |e| Some(e.message.from_uin),
+        GroupRequest: group_request_handlers: |_| None,
+        SelfInvited: self_invited_handlers: |_| None,
+        FriendRequest: friend_request_handlers: |_| None,
+        NewMember: new_member_handlers: |_| None,
+        GroupMute: group_mute_handlers: |_| None,
+        FriendMessageRecall: friend_message_recall_handlers: |_| None,
+        GroupMessageRecall: group_message_recall_handlers: |_| None,
+        NewFriend: new_friend_handlers: |_| None,
+        GroupLeave: group_leave_handlers: |_| None,
+        FriendPoke: friend_poke_handlers: |_| None,
+        GroupNameUpdate: group_name_update_handlers: |_| None,
+        DeleteFriend: delete_friend_handlers: |_| None,
+        MemberPermissionChange: member_permission_change_handlers: |_| None,
+        GroupTempMessage: group_temp_message_handlers: |e| Some(e.message.from_uin),
+        GroupDisband: group_disband_handlers: |_| None,
+        KickedOffline: kicked_offline_handlers: |_| None,
+        MSFOffline: msf_offline_handlers: |_| None
     );
 }
 
@@ -93,14 +132,17 @@ macro_rules! on_event {
     ($fname: ident,$handler: tt, $aty: ty) => {
         pub fn $fname<F, Fut>(mut self, f: F) -> Self
         where
-            F: Fn($aty) -> Fut + Copy + Send + Sync + 'static,
+            F: Fn($aty) -> Fut + Clone + Send + Sync + 'static,
             Fut: Future<Output = ()> + Send,
         {
-            let s: BoxCloneService<$aty, (), Infallible> = ServiceBuilder::new()
+            let s: BoxCloneService<$aty, (), HandlerError> = ServiceBuilder::new()
                 .boxed_clone()
-                .service_fn(move |req| async move {
-                    f(req).await;
-                    Ok(())
+                .service_fn(move |req| {
+                    let f = f.clone();
+                    async move {
+                        f(req).await;
+                        Ok::<_, HandlerError>(())
+                    }
                 });
             self.$handler.push(s);
             self
@@ -108,12 +150,83 @@ macro_rules! on_event {
     };
 }
 
+macro_rules! on_event_with {
+    ($fname: ident, $handler: tt, $aty: ty) => {
+        pub fn $fname<L, F, Fut>(mut self, layer: L, f: F) -> Self
+        where
+            F: Fn($aty) -> Fut + Clone + Send + Sync + 'static,
+            Fut: Future<Output = ()> + Send,
+            L: Layer<BoxCloneService<$aty, (), HandlerError>>,
+            L::Service: Service<$aty, Response = ()> + Clone + Send + 'static,
+            <L::Service as Service<$aty>>::Error: Into<HandlerError>,
+            <L::Service as Service<$aty>>::Future: Send,
+        {
+            let base: BoxCloneService<$aty, (), HandlerError> = ServiceBuilder::new()
+                .boxed_clone()
+                .service_fn(move |req| {
+                    let f = f.clone();
+                    async move {
+                        f(req).await;
+                        Ok::<_, HandlerError>(())
+                    }
+                });
+            let layered = layer.layer(base).map_err(Into::into);
+            self.$handler.push(BoxCloneService::new(layered));
+            self
+        }
+    };
+}
+
 impl RQServiceBuilder {
     pub fn new() -> RQServiceBuilder {
         Self::default()
     }
     pub fn build(self) -> RQService {
-        RQService(Buffer::new(self, 10))
+        let metrics = self.metrics.clone();
+        let bound = self.buffer_bound.unwrap_or(10);
+        let global = self.global_layer.clone();
+        let dispatch: DispatchService =
+            BoxCloneService::new(self.map_err(|e: Infallible| -> HandlerError { match e {} }));
+        let dispatch = match global {
+            Some(layer) => layer(dispatch),
+            None => dispatch,
+        };
+        RQService(Buffer::new(dispatch, bound), metrics)
+    }
+
+    /// The dispatch metrics registry shared with the built service.
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Set the bound of the dispatch [`Buffer`] (default `10`).
+    pub fn buffer_bound(mut self, bound: usize) -> Self {
+        self.buffer_bound = Some(bound);
+        self
+    }
+
+    /// Apply a `tower` [`Layer`] around the whole `QEvent` dispatch.
+    ///
+    /// Layers compose outermost-last, so the most recently applied layer runs
+    /// first. Use this for cross-cutting middleware such as `concurrency_limit`
+    /// or a global `timeout`.
+    pub fn layer<L>(mut self, layer: L) -> Self
+    where
+        L: Layer<DispatchService> + Send + Sync + 'static,
+        L::Service: Service<QEvent, Response = ()> + Clone + Send + 'static,
+        <L::Service as Service<QEvent>>::Error: Into<HandlerError>,
+        <L::Service as Service<QEvent>>::Future: Send,
+    {
+        let prev = self.global_layer.take();
+        let composed: GlobalLayer = Arc::new(move |svc: DispatchService| {
+            let inner = match &prev {
+                Some(prev) => prev(svc),
+                None => svc,
+            };
+            BoxCloneService::new(layer.layer(inner).map_err(Into::into))
+        });
+        self.global_layer = Some(composed);
+        self
     }
 
     on_event!(on_login, login_handlers, i64);
@@ -156,6 +269,91 @@ impl RQServiceBuilder {
         member_permission_change_handlers,
         MemberPermissionChangeEvent
     );
+    on_event!(
+        on_group_temp_message,
+        group_temp_message_handlers,
+        GroupTempMessageEvent
+    );
+    on_event!(on_group_disband, group_disband_handlers, GroupDisbandEvent);
+    // 群 "灰条" 提示（骰子 / 石头剪刀布 / 运气王等）：out-of-scope。当前依赖的
+    // rs_qq 版本没有对应的 `QEvent` 变体——协议层把这些灰条解析进 `GroupMessage`
+    // 的消息元素，而不是单独派发为事件，因此无法注册独立的 `on_group_grey_tip`
+    // 处理器。待上游暴露该变体后再补。
+    //
+    // 客户端离线：请求里写作 `on_client_offline`，这里按底层事件拆成两个更精确的
+    // 注册方法——`on_kicked_offline`（被其他端踢下线）与 `on_msf_offline`
+    // （MSF 服务器主动断开）；都可在 auto_reconnect 介入前记录/告警。
+    on_event!(on_kicked_offline, kicked_offline_handlers, KickedOfflineEvent);
+    on_event!(on_msf_offline, msf_offline_handlers, MSFOfflineEvent);
+
+    on_event_with!(on_login_with, login_handlers, i64);
+    on_event_with!(
+        on_group_message_with,
+        group_message_handlers,
+        GroupMessageEvent
+    );
+    on_event_with!(
+        on_private_message_with,
+        private_message_handlers,
+        PrivateMessageEvent
+    );
+    on_event_with!(
+        on_group_request_with,
+        group_request_handlers,
+        GroupRequestEvent
+    );
+    on_event_with!(on_self_invited_with, self_invited_handlers, SelfInvitedEvent);
+    on_event_with!(
+        on_friend_request_with,
+        friend_request_handlers,
+        FriendRequestEvent
+    );
+    on_event_with!(on_new_member_with, new_member_handlers, NewMemberEvent);
+    on_event_with!(on_group_mute_with, group_mute_handlers, GroupMuteEvent);
+    on_event_with!(
+        on_friend_message_recall_with,
+        friend_message_recall_handlers,
+        FriendMessageRecallEvent
+    );
+    on_event_with!(
+        on_group_message_recall_with,
+        group_message_recall_handlers,
+        GroupMessageRecallEvent
+    );
+    on_event_with!(on_new_friend_with, new_friend_handlers, NewFriendEvent);
+    on_event_with!(on_group_leave_with, group_leave_handlers, GroupLeaveEvent);
+    on_event_with!(on_friend_poke_with, friend_poke_handlers, FriendPokeEvent);
+    on_event_with!(
+        on_group_name_update_with,
+        group_name_update_handlers,
+        GroupNameUpdateEvent
+    );
+    on_event_with!(
+        on_delete_friend_with,
+        delete_friend_handlers,
+        DeleteFriendEvent
+    );
+    on_event_with!(
+        on_member_permission_change_with,
+        member_permission_change_handlers,
+        MemberPermissionChangeEvent
+    );
+    on_event_with!(
+        on_group_temp_message_with,
+        group_temp_message_handlers,
+        GroupTempMessageEvent
+    );
+    on_event_with!(
+        on_group_disband_with,
+        group_disband_handlers,
+        GroupDisbandEvent
+    );
+    on_event_with!(
+        on_kicked_offline_with,
+        kicked_offline_handlers,
+        KickedOfflineEvent
+    );
+    on_event_with!(on_msf_offline_with, msf_offline_handlers, MSFOfflineEvent);
 }
 
 #[allow(clippy::type_complexity)]
@@ -5,8 +5,9 @@ use async_trait::async_trait;
 use tower::buffer::Buffer;
 use tower::{Service, ServiceExt};
 
+use crate::metrics::Metrics;
 use crate::rq::handler::{Handler, QEvent};
-use crate::service::builder::RQServiceBuilder;
+use crate::service::builder::DispatchService;
 
 pub mod builder;
 #[async_trait]
@@ -22,7 +23,14 @@ impl Handler for RQService {
     }
 }
 
-pub struct RQService(Buffer<RQServiceBuilder, QEvent>);
+pub struct RQService(Buffer<DispatchService, QEvent>, Metrics);
+
+impl RQService {
+    /// Dispatch metrics collected across all handler invocations.
+    pub fn metrics(&self) -> &Metrics {
+        &self.1
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -32,6 +40,7 @@ mod tests {
     use tower::{Service, ServiceBuilder, ServiceExt};
 
     use super::*;
+    use crate::service::builder::RQServiceBuilder;
 
     #[tokio::test]
     async fn t() {
@@ -0,0 +1,197 @@
+// argon2 = "0.5"
+// chacha20poly1305 = "0.10"
+// rand = "0.8"
+
+//! Passphrase-protected, encrypted-at-rest session storage.
+//!
+//! Writing `device.json` in plaintext and handing the reconnect token back
+//! raw would give anyone with filesystem access a full session.
+//! [`SessionStore`] instead seals the serialized [`Device`] and
+//! reconnect [`Token`] with an AEAD keyed by a passphrase.
+//!
+//! The on-disk layout is `salt(16) ‖ nonce(24) ‖ ciphertext`:
+//! a symmetric key is derived from the passphrase with Argon2id over the random
+//! salt, and the payload is sealed with XChaCha20-Poly1305 under a fresh random
+//! nonce. Loading re-derives the key and decrypts, surfacing a clear error on a
+//! wrong passphrase or a tampered file.
+
+use std::path::{Path, PathBuf};
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::rq::device::Device;
+use crate::rq::ext::reconnect::Token;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const KEY_LEN: usize = 32;
+
+/// A reconnectable session: the device fingerprint plus the last reconnect token.
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    pub device: Device,
+    pub token: Option<Token>,
+}
+
+/// Argon2id cost parameters used to derive the sealing key.
+#[derive(Clone, Copy)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        // Matches the `argon2` crate's default costs (19 MiB, 2 passes, 1 lane).
+        Self {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+/// Errors produced while sealing or opening a session.
+#[derive(Debug)]
+pub enum SessionError {
+    Io(std::io::Error),
+    Serde(serde_json::Error),
+    /// Key derivation failed for the given parameters.
+    Kdf,
+    /// The file is shorter than the salt + nonce header.
+    Truncated,
+    /// Decryption failed — wrong passphrase or a tampered file.
+    Decrypt,
+}
+
+impl std::fmt::Display for SessionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SessionError::Io(e) => write!(f, "io error: {}", e),
+            SessionError::Serde(e) => write!(f, "serialization error: {}", e),
+            SessionError::Kdf => write!(f, "failed to derive key"),
+            SessionError::Truncated => write!(f, "session file is truncated"),
+            SessionError::Decrypt => write!(f, "bad passphrase or tampered session file"),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+impl From<std::io::Error> for SessionError {
+    fn from(e: std::io::Error) -> Self {
+        SessionError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for SessionError {
+    fn from(e: serde_json::Error) -> Self {
+        SessionError::Serde(e)
+    }
+}
+
+/// Persists a [`Session`] so credentials never touch disk unencrypted.
+pub trait SessionStore {
+    /// Seal and write the session.
+    fn save(&self, session: &Session) -> Result<(), SessionError>;
+    /// Read and decrypt the session, or `None` if nothing has been stored yet.
+    fn load(&self) -> Result<Option<Session>, SessionError>;
+}
+
+/// Default [`SessionStore`] backed by a single encrypted file.
+pub struct FileSessionStore {
+    path: PathBuf,
+    passphrase: Vec<u8>,
+    params: KdfParams,
+}
+
+impl FileSessionStore {
+    /// Store sealed under `passphrase` at `path` using the default KDF cost.
+    pub fn new(path: impl Into<PathBuf>, passphrase: impl Into<String>) -> Self {
+        Self::with_params(path, passphrase, KdfParams::default())
+    }
+
+    /// Store sealed under `passphrase` at `path` with explicit Argon2id costs.
+    pub fn with_params(
+        path: impl Into<PathBuf>,
+        passphrase: impl Into<String>,
+        params: KdfParams,
+    ) -> Self {
+        Self {
+            path: path.into(),
+            passphrase: passphrase.into().into_bytes(),
+            params,
+        }
+    }
+
+    fn derive_key(&self, salt: &[u8]) -> Result<[u8; KEY_LEN], SessionError> {
+        let params = Params::new(
+            self.params.memory_kib,
+            self.params.iterations,
+            self.params.parallelism,
+            Some(KEY_LEN),
+        )
+        .map_err(|_| SessionError::Kdf)?;
+        let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+        let mut key = [0u8; KEY_LEN];
+        argon2
+            .hash_password_into(&self.passphrase, salt, &mut key)
+            .map_err(|_| SessionError::Kdf)?;
+        Ok(key)
+    }
+}
+
+impl SessionStore for FileSessionStore {
+    fn save(&self, session: &Session) -> Result<(), SessionError> {
+        let plaintext = serde_json::to_vec(session)?;
+
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        let mut rng = rand::thread_rng();
+        rng.fill_bytes(&mut salt);
+        rng.fill_bytes(&mut nonce);
+
+        let key = self.derive_key(&salt)?;
+        let cipher = XChaCha20Poly1305::new(key.as_ref().into());
+        let ciphertext = cipher
+            .encrypt(XNonce::from_slice(&nonce), plaintext.as_ref())
+            .map_err(|_| {
+                SessionError::Io(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    "failed to encrypt session",
+                ))
+            })?;
+
+        let mut out = Vec::with_capacity(SALT_LEN + NONCE_LEN + ciphertext.len());
+        out.extend_from_slice(&salt);
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&ciphertext);
+        std::fs::write(&self.path, out)?;
+        Ok(())
+    }
+
+    fn load(&self) -> Result<Option<Session>, SessionError> {
+        if !Path::new(&self.path).exists() {
+            return Ok(None);
+        }
+        let raw = std::fs::read(&self.path)?;
+        if raw.len() < SALT_LEN + NONCE_LEN {
+            return Err(SessionError::Truncated);
+        }
+        let (salt, rest) = raw.split_at(SALT_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+        let key = self.derive_key(salt)?;
+        let cipher = XChaCha20Poly1305::new(key.as_ref().into());
+        let plaintext = cipher
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| SessionError::Decrypt)?;
+        let session = serde_json::from_slice(&plaintext)?;
+        Ok(Some(session))
+    }
+}